@@ -5,28 +5,35 @@ use rdev::{
 };
 pub static mut IS_GRAB: bool = false;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashSet,
     mem::zeroed,
-    os::raw::c_int,
+    os::raw::{c_int, c_uchar},
     ptr,
     sync::{mpsc::Sender, Arc, Mutex},
     thread,
     time::SystemTime,
 };
-use strum::IntoEnumIterator;
-use x11::xlib::{self, Display, GrabModeAsync, KeyPressMask, XUngrabKey};
-
-const KEYPRESS_EVENT: i32 = 2;
-const MODIFIERS: i32 = 0;
+use x11::{
+    xinput2,
+    xlib::{self, Display},
+};
 
 static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event) -> Option<Event>>> = None;
 
 lazy_static::lazy_static! {
-    pub static ref GRABED: Arc<Mutex<HashSet<RdevKey>>> = Arc::new(Mutex::new(HashSet::<RdevKey>::new()));
+    /// Keycodes currently under an active `XIGrabKeycode` suppression grab,
+    /// i.e. keys whose last callback invocation returned `None`.
+    pub static ref GRABED: Arc<Mutex<HashSet<i32>>> = Arc::new(Mutex::new(HashSet::<i32>::new()));
     pub static ref BROADCAST_CONNECT: Arc<Mutex<Option<Sender<bool>>>> = Arc::new(Mutex::new(None));
 }
 
-fn convert_event(key: RdevKey, is_press: bool) -> Event {
+/// `raw_keycode` is the genuine hardware keycode the X server reported, not
+/// rdev's canonical mapping for `key` — it's threaded through into
+/// `scan_code` so callers can key off the physical key that was pressed
+/// (important on non-US layouts, where the same `Key` can sit at different
+/// hardware positions). `code` keeps carrying rdev's logical keycode for
+/// `key`, as before.
+fn convert_event(key: RdevKey, is_press: bool, raw_keycode: i32) -> Event {
     Event {
         event_type: if is_press {
             EventType::KeyPress(key)
@@ -36,60 +43,68 @@ fn convert_event(key: RdevKey, is_press: bool) -> Event {
         time: SystemTime::now(),
         name: None,
         code: linux_keycode_from_key(key).unwrap_or_default() as _,
-        scan_code: linux_keycode_from_key(key).unwrap_or_default() as _,
+        scan_code: raw_keycode as _,
+        // X11 has no equivalent of dwExtraInfo tagging in this grab path.
+        injected: false,
+        physical_key: Some(key_from_scancode(raw_keycode as _)),
     }
 }
 
-fn ungrab_key(display: *mut Display, grab_window: u64, keycode: i32) {
-    unsafe {
-        XUngrabKey(display, keycode, MODIFIERS as _, grab_window);
-    }
-}
-
-fn ungrab_keys(display: *mut Display, grab_window: u64) {
-    for key in RdevKey::iter() {
-        let keycode: i32 = linux_keycode_from_key(key).unwrap_or_default() as _;
-        if is_key_grabed(key) {
-            grab_key(display, grab_window, keycode);
-            GRABED.lock().unwrap().insert(key);
-        }
-    }
+fn is_key_grabed(keycode: i32) -> bool {
+    GRABED.lock().unwrap().contains(&keycode)
 }
 
+/// Issues an `XIGrabKeycode` for `keycode` so the OS stops delivering it to
+/// the focused window, and records it in `GRABED` so it isn't grabbed twice
+/// and can be released again by `ungrab_keys`.
 fn grab_key(display: *mut Display, grab_window: u64, keycode: i32) {
+    if is_key_grabed(keycode) {
+        return;
+    }
+    let mut mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: 0,
+        mask: ptr::null_mut(),
+    };
+    // The modifiers array is what actually gets grabbed, so passing zero
+    // entries would grab nothing at all. Pass XIAnyModifier so the key is
+    // grabbed regardless of whatever modifiers happen to be held.
+    let mut modifiers = xinput2::XIGrabModifiers {
+        modifiers: xinput2::XIAnyModifier as i32,
+        status: 0,
+    };
     unsafe {
-        xlib::XGrabKey(
+        xinput2::XIGrabKeycode(
             display,
+            xinput2::XIAllMasterDevices,
             keycode,
-            MODIFIERS as _,
             grab_window,
+            xlib::GrabModeAsync,
+            xlib::GrabModeAsync,
             c_int::from(true),
-            GrabModeAsync,
-            GrabModeAsync,
+            &mut mask,
+            1,
+            &mut modifiers,
         );
     }
+    GRABED.lock().unwrap().insert(keycode);
 }
 
-fn is_key_grabed(key: RdevKey) -> bool {
-    GRABED.lock().unwrap().get(&key).is_some()
+/// Releases a single keycode previously grabbed by `grab_key`.
+fn ungrab_key(display: *mut Display, grab_window: u64, keycode: i32) {
+    unsafe {
+        xinput2::XIUngrabKeycode(display, xinput2::XIAllMasterDevices, keycode, grab_window);
+    }
+    GRABED.lock().unwrap().remove(&keycode);
 }
 
-fn grab_keys(display: *mut Display, grab_window: u64) {
-    for key in RdevKey::iter() {
-        let event = convert_event(key, true);
-
-        unsafe {
-            if let Some(callback) = &mut GLOBAL_CALLBACK {
-                let grab = callback(event).is_none();
-                let keycode: i32 = linux_keycode_from_key(key).unwrap_or_default() as _;
-
-                if grab && !is_key_grabed(key) {
-                    println!("{:?} {:?}", key, keycode);
-                    grab_key(display, grab_window, keycode);
-                    // GRABED.lock().unwrap().insert(key);
-                }
-            }
-        }
+/// Releases every keycode currently under an active suppression grab, so
+/// `IS_GRAB = false` cleanly tears the whole interceptor down instead of
+/// leaving stray `XIGrabKeycode`s behind.
+fn ungrab_keys(display: *mut Display, grab_window: u64) {
+    let grabed: Vec<i32> = GRABED.lock().unwrap().iter().copied().collect();
+    for keycode in grabed {
+        ungrab_key(display, grab_window, keycode);
     }
 }
 
@@ -110,42 +125,85 @@ fn send_key(key: RdevKey, is_press: bool) {
     thread::sleep(delay);
 }
 
+/// Selects `XI_RawKeyPress`/`XI_RawKeyRelease` on the root window through a
+/// single XInput2 connection, replacing the old per-key `XGrabKey` loop with
+/// one blocking `XNextEvent` that receives every key event system-wide.
+/// Suppression (the callback returning `None`) is done on demand through an
+/// active `XIGrabKeycode`, so only keys a caller actually wants consumed pay
+/// for a grab, and `ungrab_keys` releases them when the hook is torn down.
 fn set_key_hook() {
     unsafe {
         let display = xlib::XOpenDisplay(ptr::null());
         let screen_number = xlib::XDefaultScreen(display);
         let screen = xlib::XScreenOfDisplay(display, screen_number);
         let grab_window = xlib::XRootWindowOfScreen(screen);
-        let my_grab_window = grab_window;
-
-        loop {
-            if IS_GRAB {
-                let handle = std::thread::spawn(move || {
-                    let display = xlib::XOpenDisplay(ptr::null());
-                    grab_keys(display, my_grab_window);
-
-                    xlib::XSelectInput(display, grab_window, KeyPressMask);
-                    let mut x_event: xlib::XEvent = zeroed();
-                    loop {
-                        if !IS_GRAB {
-                            break;
-                        }
-                        xlib::XNextEvent(display, &mut x_event);
-
-                        let key = key_from_scancode(x_event.key.keycode);
-                        let is_press = x_event.type_ == KEYPRESS_EVENT;
-                        let event = convert_event(key, is_press);
-
-                        if let Some(callback) = &mut GLOBAL_CALLBACK {
-                            let _grab = callback(event).is_none();
-                        }
-
-                        println!("{:?} {:?}", key, is_press);
-                    }
-                });
-                handle.join();
+
+        let mut xi_opcode: c_int = 0;
+        let mut event: c_int = 0;
+        let mut error: c_int = 0;
+        let has_xinput2 = xlib::XQueryExtension(
+            display,
+            b"XInputExtension\0".as_ptr() as *const _,
+            &mut xi_opcode,
+            &mut event,
+            &mut error,
+        ) != 0;
+        if !has_xinput2 {
+            eprintln!("XInput2 extension is not available on this X server");
+            return;
+        }
+
+        let mut mask_bytes: [c_uchar; (xinput2::XI_LASTEVENT as usize / 8) + 1] =
+            [0; (xinput2::XI_LASTEVENT as usize / 8) + 1];
+        xinput2::XISetMask(&mut mask_bytes, xinput2::XI_RawKeyPress);
+        xinput2::XISetMask(&mut mask_bytes, xinput2::XI_RawKeyRelease);
+        let mut mask = xinput2::XIEventMask {
+            // Raw events are only ever delivered to master devices.
+            deviceid: xinput2::XIAllMasterDevices,
+            mask_len: mask_bytes.len() as c_int,
+            mask: mask_bytes.as_mut_ptr(),
+        };
+        xinput2::XISelectEvents(display, grab_window, &mut mask, 1);
+        xlib::XFlush(display);
+
+        while IS_GRAB {
+            let mut x_event: xlib::XEvent = zeroed();
+            xlib::XNextEvent(display, &mut x_event);
+
+            if x_event.get_type() != xlib::GenericEvent
+                || x_event.generic_event_cookie.extension != xi_opcode
+            {
+                continue;
+            }
+            if xlib::XGetEventData(display, &mut x_event.generic_event_cookie) == 0 {
+                continue;
+            }
+
+            let is_press = x_event.generic_event_cookie.evtype == xinput2::XI_RawKeyPress;
+            let is_release = x_event.generic_event_cookie.evtype == xinput2::XI_RawKeyRelease;
+            if is_press || is_release {
+                let raw_event = &*(x_event.generic_event_cookie.data as *const xinput2::XIRawEvent);
+                let keycode = raw_event.detail;
+                let key = key_from_scancode(keycode as _);
+                let rdev_event = convert_event(key, is_press, keycode);
+
+                let suppress = if let Some(callback) = &mut GLOBAL_CALLBACK {
+                    callback(rdev_event).is_none()
+                } else {
+                    false
+                };
+
+                if suppress {
+                    grab_key(display, grab_window, keycode);
+                } else if is_key_grabed(keycode) {
+                    ungrab_key(display, grab_window, keycode);
+                }
             }
+
+            xlib::XFreeEventData(display, &mut x_event.generic_event_cookie);
         }
+
+        ungrab_keys(display, grab_window);
     }
 }
 
@@ -155,6 +213,7 @@ where
 {
     unsafe {
         GLOBAL_CALLBACK = Some(Box::new(callback));
+        IS_GRAB = true;
     }
     set_key_hook();
     Ok(())
@@ -0,0 +1,174 @@
+use crate::{grab, Event, EventType, GrabError, Key};
+use std::collections::HashSet;
+use std::fmt;
+use strum::IntoEnumIterator;
+
+/// A normalized keyboard shortcut: a set of modifier keys plus the key that
+/// triggers it, e.g. `Ctrl+Shift+K`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    pub mods: HashSet<Key>,
+    pub key: Key,
+}
+
+/// Why an accelerator string in [`parse_accelerator`] could not be turned
+/// into a [`Hotkey`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceleratorParseError {
+    /// A `+`-separated token didn't match any known modifier or key name.
+    UnknownToken(String),
+    /// The string had no final key token, only modifiers (or was empty).
+    MissingKey,
+    /// The same modifier appeared more than once, e.g. `"Ctrl+Ctrl+K"`.
+    DuplicateModifier(String),
+}
+
+impl fmt::Display for AcceleratorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcceleratorParseError::UnknownToken(token) => {
+                write!(f, "unknown accelerator token: {}", token)
+            }
+            AcceleratorParseError::MissingKey => {
+                write!(f, "accelerator is missing a non-modifier key")
+            }
+            AcceleratorParseError::DuplicateModifier(token) => {
+                write!(f, "modifier specified more than once: {}", token)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AcceleratorParseError {}
+
+/// `listen`/`grab` report distinct `Left`/`Right` keys for Ctrl, Shift and
+/// Meta, but an accelerator like `"Ctrl+K"` is meant to match either side.
+/// Returns the sibling of a `Left`/`Right` modifier `Key`, if it has one.
+fn modifier_sibling(key: Key) -> Option<Key> {
+    match key {
+        Key::ControlLeft => Some(Key::ControlRight),
+        Key::ControlRight => Some(Key::ControlLeft),
+        Key::ShiftLeft => Some(Key::ShiftRight),
+        Key::ShiftRight => Some(Key::ShiftLeft),
+        Key::MetaLeft => Some(Key::MetaRight),
+        Key::MetaRight => Some(Key::MetaLeft),
+        _ => None,
+    }
+}
+
+/// Whether `held` contains `modifier` or, for Ctrl/Shift/Meta, its
+/// opposite-side sibling.
+fn held_contains_modifier(held: &HashSet<Key>, modifier: Key) -> bool {
+    held.contains(&modifier)
+        || modifier_sibling(modifier).is_some_and(|sibling| held.contains(&sibling))
+}
+
+fn modifier_from_token(token: &str) -> Option<Key> {
+    match token {
+        "ctrl" | "control" => Some(Key::ControlLeft),
+        "alt" => Some(Key::Alt),
+        "shift" => Some(Key::ShiftLeft),
+        "meta" | "super" | "cmd" => Some(Key::MetaLeft),
+        _ => None,
+    }
+}
+
+fn key_from_token(token: &str) -> Option<Key> {
+    // Resolve against Key's variants (via `Key::iter`) case-insensitively,
+    // so "k", "K" and "F5" all resolve the same way.
+    let mut chars = token.chars();
+    let first = chars.next()?;
+    let normalized: String = first.to_uppercase().chain(chars).collect();
+
+    // Most variants (`Escape`, `Space`, `F5`, `CapsLock`, ...) match the
+    // capitalized token directly, but single letters/digits are prefixed in
+    // `Key`'s Debug output (`KeyK`, `Num1`), so try those forms too.
+    let candidates = [
+        normalized.clone(),
+        format!("Key{}", normalized),
+        format!("Num{}", normalized),
+    ];
+    Key::iter().find(|key| {
+        let debug = format!("{:?}", key);
+        candidates
+            .iter()
+            .any(|candidate| debug.eq_ignore_ascii_case(candidate))
+    })
+}
+
+/// Parses a human-readable accelerator such as `"Ctrl+Shift+K"` or
+/// `"Alt+F5"` into a [`Hotkey`]. Tokens are split on `+` and compared
+/// case-insensitively; every token but the last must name a modifier
+/// (`ctrl`/`control`, `alt`, `shift`, `meta`/`super`/`cmd`), and the last
+/// token must name a [`Key`].
+pub fn parse_accelerator(accelerator: &str) -> Result<Hotkey, AcceleratorParseError> {
+    let tokens: Vec<&str> = accelerator.split('+').map(str::trim).collect();
+    let (key_token, mod_tokens) = tokens
+        .split_last()
+        .ok_or(AcceleratorParseError::MissingKey)?;
+    if key_token.is_empty() || modifier_from_token(&key_token.to_lowercase()).is_some() {
+        return Err(AcceleratorParseError::MissingKey);
+    }
+
+    let mut mods = HashSet::new();
+    for token in mod_tokens {
+        let lower = token.to_lowercase();
+        let modifier = modifier_from_token(&lower)
+            .ok_or_else(|| AcceleratorParseError::UnknownToken(token.to_string()))?;
+        if !mods.insert(modifier) {
+            return Err(AcceleratorParseError::DuplicateModifier(token.to_string()));
+        }
+    }
+
+    let key = key_from_token(&key_token.to_lowercase())
+        .ok_or_else(|| AcceleratorParseError::UnknownToken(key_token.to_string()))?;
+
+    Ok(Hotkey { mods, key })
+}
+
+fn is_fully_pressed(hotkey: &Hotkey, held: &HashSet<Key>) -> bool {
+    held.contains(&hotkey.key)
+        && hotkey
+            .mods
+            .iter()
+            .all(|modifier| held_contains_modifier(held, *modifier))
+}
+
+/// Registers global hotkeys on top of [`grab`]: tracks currently-held keys
+/// and fires `callback` with the matching [`Hotkey`] on the transition into
+/// every key in one of `hotkeys` being simultaneously pressed. Once fired, a
+/// hotkey stays quiet — including across OS key-repeat `KeyPress`es — until
+/// it's released and pressed again. Like any `grab` callback, all other key
+/// events are still passed through to the OS.
+pub fn register_hotkeys<T>(hotkeys: Vec<Hotkey>, mut callback: T) -> Result<(), GrabError>
+where
+    T: FnMut(&Hotkey) + 'static,
+{
+    let mut held: HashSet<Key> = HashSet::new();
+    let mut active: HashSet<usize> = HashSet::new();
+    grab(move |event: Event| -> Option<Event> {
+        match event.event_type {
+            EventType::KeyPress(key) => {
+                held.insert(key);
+                for (index, hotkey) in hotkeys.iter().enumerate() {
+                    let is_hotkey_key = hotkey.key == key
+                        || hotkey.mods.iter().any(|modifier| {
+                            *modifier == key || modifier_sibling(*modifier) == Some(key)
+                        });
+                    if is_hotkey_key && is_fully_pressed(hotkey, &held) {
+                        if active.insert(index) {
+                            callback(hotkey);
+                        }
+                        return None;
+                    }
+                }
+            }
+            EventType::KeyRelease(key) => {
+                held.remove(&key);
+                active.retain(|&index| is_fully_pressed(&hotkeys[index], &held));
+            }
+            _ => {}
+        }
+        Some(event)
+    })
+}
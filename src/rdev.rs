@@ -0,0 +1,189 @@
+use std::time::SystemTime;
+use strum_macros::EnumIter;
+
+/// A physical or virtual key, identified the way the OS reports it rather
+/// than by the character it produces. The `Key` in a `KeyPress`/`KeyRelease`
+/// can follow the active layout on some platforms; `Event::physical_key`
+/// gives the layout-independent position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum Key {
+    Alt,
+    AltGr,
+    Backspace,
+    CapsLock,
+    ControlLeft,
+    ControlRight,
+    Delete,
+    DownArrow,
+    End,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Home,
+    LeftArrow,
+    MetaLeft,
+    MetaRight,
+    PageDown,
+    PageUp,
+    Return,
+    RightArrow,
+    ShiftLeft,
+    ShiftRight,
+    Space,
+    Tab,
+    UpArrow,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+    NumLock,
+    BackQuote,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    Num0,
+    Minus,
+    Equal,
+    KeyQ,
+    KeyW,
+    KeyE,
+    KeyR,
+    KeyT,
+    KeyY,
+    KeyU,
+    KeyI,
+    KeyO,
+    KeyP,
+    LeftBracket,
+    RightBracket,
+    KeyA,
+    KeyS,
+    KeyD,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyJ,
+    KeyK,
+    KeyL,
+    SemiColon,
+    Quote,
+    BackSlash,
+    IntlBackslash,
+    KeyZ,
+    KeyX,
+    KeyC,
+    KeyV,
+    KeyB,
+    KeyN,
+    KeyM,
+    Comma,
+    Dot,
+    Slash,
+    Insert,
+    KpReturn,
+    KpMinus,
+    KpPlus,
+    KpMultiply,
+    KpDivide,
+    Kp0,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpDelete,
+    Function,
+    Unknown(u32),
+}
+
+/// A mouse button. `Unknown` carries the platform button code for side/extra
+/// buttons that don't map to left/middle/right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    Left,
+    Middle,
+    Right,
+    Unknown(u8),
+}
+
+/// What happened: a key or button transition, a wheel tick, or a cursor
+/// move. `simulate`/`simulate_batch` consume these; `listen`/`grab` produce
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventType {
+    KeyPress(Key),
+    KeyRelease(Key),
+    ButtonPress(Button),
+    ButtonRelease(Button),
+    Wheel { delta_x: i64, delta_y: i64 },
+    MouseMove { x: f64, y: f64 },
+}
+
+/// A captured input event, as delivered to a `listen`/`grab` callback.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub event_type: EventType,
+    pub time: SystemTime,
+    pub name: Option<String>,
+    /// rdev's logical keycode for the key involved, independent of the
+    /// physical key that produced it.
+    pub code: u32,
+    /// The genuine hardware scancode reported by the OS.
+    pub scan_code: u32,
+    /// Whether this event carries rdev's own synthetic-input signature
+    /// (Windows: `dwExtraInfo`), i.e. it was produced by a `simulate` call
+    /// rather than real hardware. Always `false` on platforms that don't
+    /// surface the distinction.
+    pub injected: bool,
+    /// For key events, the key at the physical position that was pressed,
+    /// resolved from the hardware scancode rather than from `code`/layout.
+    /// Unlike `EventType::KeyPress`'s `Key`, this stays the same across
+    /// layouts, so games and remapping tools can key off position instead of
+    /// label. `None` for non-keyboard events.
+    pub physical_key: Option<Key>,
+}
+
+/// Returned by `simulate`/`simulate_batch` when the platform failed to
+/// inject some or all of the requested input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulateError {
+    /// How many `INPUT`s were actually injected before the call fell short,
+    /// so callers can tell a clean failure from a partial one. This counts
+    /// `INPUT`s, not events, for `simulate_batch` too: one event can expand
+    /// to zero, one or two `INPUT`s.
+    pub injected: usize,
+}
+
+/// Returned by `grab`/`listen`/`register_hotkeys` when the platform hook
+/// could not be installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrabError {
+    HookError(String),
+    MissingDisplayError,
+    SimulateError,
+}
+
+impl From<SimulateError> for GrabError {
+    fn from(_: SimulateError) -> Self {
+        GrabError::SimulateError
+    }
+}
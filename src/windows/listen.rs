@@ -0,0 +1,139 @@
+use crate::rdev::{Button, Event, EventType, GrabError};
+use crate::windows::keycodes::{key_from_code, key_from_scancode};
+use std::convert::TryInto;
+use std::os::raw::c_int;
+use std::ptr::null_mut;
+use std::time::SystemTime;
+use winapi::shared::basetsd::ULONG_PTR;
+use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
+use winapi::shared::windef::HHOOK;
+use winapi::um::winuser::{
+    CallNextHookEx, GetMessageW, SetWindowsHookExW, KBDLLHOOKSTRUCT, MSG, MSLLHOOKSTRUCT,
+    WH_KEYBOARD_LL, WH_MOUSE_LL, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP,
+    WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP,
+    WM_SYSKEYDOWN, WM_SYSKEYUP,
+};
+
+/// Must match `RDEV_INJECTED_SIGNATURE` in `windows::simulate` so the hook
+/// recognizes rdev's own injected input.
+const RDEV_INJECTED_SIGNATURE: ULONG_PTR = 0x5244_4556;
+
+static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event) + 'static>> = None;
+
+fn is_rdev_injected(extra_info: ULONG_PTR) -> bool {
+    extra_info == RDEV_INJECTED_SIGNATURE
+}
+
+fn keyboard_event(code: u32, scan_code: u32, is_press: bool, injected: bool) -> Event {
+    // `code` is the virtual-key rdev's logical mapping resolves to; unlike
+    // it, `physical_key` is resolved straight from the hardware scancode, so
+    // it stays stable across keyboard layouts.
+    let key = key_from_code(code as _);
+    let physical_key = key_from_scancode(scan_code as _);
+    Event {
+        event_type: if is_press {
+            EventType::KeyPress(key)
+        } else {
+            EventType::KeyRelease(key)
+        },
+        time: SystemTime::now(),
+        name: None,
+        code,
+        scan_code,
+        injected,
+        physical_key: Some(physical_key),
+    }
+}
+
+fn mouse_event(event_type: EventType, injected: bool) -> Event {
+    Event {
+        event_type,
+        time: SystemTime::now(),
+        name: None,
+        code: 0,
+        scan_code: 0,
+        injected,
+        physical_key: None,
+    }
+}
+
+unsafe extern "system" fn keyboard_hook_proc(
+    code: c_int,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if code >= 0 {
+        let hook_struct = &*(lparam as *const KBDLLHOOKSTRUCT);
+        let is_press = wparam as u32 == WM_KEYDOWN || wparam as u32 == WM_SYSKEYDOWN;
+        let is_release = wparam as u32 == WM_KEYUP || wparam as u32 == WM_SYSKEYUP;
+        if is_press || is_release {
+            let injected = is_rdev_injected(hook_struct.dwExtraInfo);
+            let event =
+                keyboard_event(hook_struct.vkCode, hook_struct.scanCode, is_press, injected);
+            if let Some(callback) = &mut GLOBAL_CALLBACK {
+                callback(event);
+            }
+        }
+    }
+    CallNextHookEx(null_mut(), code, wparam, lparam)
+}
+
+unsafe extern "system" fn mouse_hook_proc(code: c_int, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let hook_struct = &*(lparam as *const MSLLHOOKSTRUCT);
+        let injected = is_rdev_injected(hook_struct.dwExtraInfo);
+        let event_type = match wparam as u32 {
+            WM_MOUSEMOVE => Some(EventType::MouseMove {
+                x: hook_struct.pt.x as f64,
+                y: hook_struct.pt.y as f64,
+            }),
+            WM_LBUTTONDOWN => Some(EventType::ButtonPress(Button::Left)),
+            WM_LBUTTONUP => Some(EventType::ButtonRelease(Button::Left)),
+            WM_RBUTTONDOWN => Some(EventType::ButtonPress(Button::Right)),
+            WM_RBUTTONUP => Some(EventType::ButtonRelease(Button::Right)),
+            WM_MBUTTONDOWN => Some(EventType::ButtonPress(Button::Middle)),
+            WM_MBUTTONUP => Some(EventType::ButtonRelease(Button::Middle)),
+            WM_MOUSEWHEEL => {
+                let delta = (hook_struct.mouseData >> 16) as i16;
+                Some(EventType::Wheel {
+                    delta_x: 0,
+                    delta_y: delta.try_into().unwrap_or(0),
+                })
+            }
+            _ => None,
+        };
+        if let Some(event_type) = event_type {
+            if let Some(callback) = &mut GLOBAL_CALLBACK {
+                callback(mouse_event(event_type, injected));
+            }
+        }
+    }
+    CallNextHookEx(null_mut(), code, wparam, lparam)
+}
+
+/// Installs `WH_KEYBOARD_LL`/`WH_MOUSE_LL` hooks and runs the message loop
+/// that drives them, invoking `callback` for every captured `Event`. Each
+/// `Event.injected` reflects whether the input carried rdev's own
+/// `dwExtraInfo` signature (see `windows::simulate::set_tag_injected_events`),
+/// so a listener can filter out its own `simulate`d input instead of
+/// reacting to it.
+pub fn listen<T>(callback: T) -> Result<(), GrabError>
+where
+    T: FnMut(Event) + 'static,
+{
+    unsafe {
+        GLOBAL_CALLBACK = Some(Box::new(callback));
+
+        let keyboard_hook: HHOOK =
+            SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), null_mut(), 0);
+        let mouse_hook: HHOOK =
+            SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook_proc), null_mut(), 0);
+        if keyboard_hook.is_null() || mouse_hook.is_null() {
+            return Err(GrabError::HookError("SetWindowsHookExW failed".to_string()));
+        }
+
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, null_mut(), 0, 0) > 0 {}
+    }
+    Ok(())
+}
@@ -3,6 +3,7 @@ use crate::windows::keycodes::get_win_codes;
 use std::convert::{TryFrom, TryInto};
 use std::mem::size_of;
 use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
 use winapi::ctypes::{c_int, c_short};
 use winapi::shared::minwindef::{DWORD, LOWORD, UINT, WORD};
 use winapi::shared::ntdef::LONG;
@@ -22,7 +23,30 @@ static KEYUP: u16 = 0x0002;
 static KEYDOWN: u16 = 0;
 static UNICODE: u16 = 0x0004;
 
-fn sim_mouse_event(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> Result<(), SimulateError> {
+/// Written into every injected event's `dwExtraInfo` so a `listen`/`grab`
+/// hook running in the same process (or another one) can recognize rdev's
+/// own synthetic input and ignore it instead of reacting to its own
+/// `simulate` calls.
+const RDEV_INJECTED_SIGNATURE: winapi::shared::basetsd::ULONG_PTR = 0x5244_4556;
+
+static TAG_INJECTED_EVENTS: AtomicBool = AtomicBool::new(true);
+
+/// Turns tagging injected events with [`RDEV_INJECTED_SIGNATURE`] on or off.
+/// Tagging is on by default; pass `false` to opt out, for callers who
+/// deliberately want `listen`/`grab` to observe their own `simulate`d input.
+pub fn set_tag_injected_events(enabled: bool) {
+    TAG_INJECTED_EVENTS.store(enabled, Ordering::SeqCst);
+}
+
+fn extra_info() -> winapi::shared::basetsd::ULONG_PTR {
+    if TAG_INJECTED_EVENTS.load(Ordering::SeqCst) {
+        RDEV_INJECTED_SIGNATURE
+    } else {
+        0
+    }
+}
+
+fn mouse_input(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> INPUT {
     let mut union: INPUT_u = unsafe { std::mem::zeroed() };
     let inner_union = unsafe { union.mi_mut() };
     *inner_union = MOUSEINPUT {
@@ -31,27 +55,15 @@ fn sim_mouse_event(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> Result<(),
         mouseData: data,
         dwFlags: flags,
         time: 0,
-        dwExtraInfo: 0,
+        dwExtraInfo: extra_info(),
     };
-    let mut input = [INPUT {
+    INPUT {
         type_: INPUT_MOUSE,
         u: union,
-    }; 1];
-    let value = unsafe {
-        SendInput(
-            input.len() as UINT,
-            input.as_mut_ptr(),
-            size_of::<INPUT>() as c_int,
-        )
-    };
-    if value != 1 {
-        Err(SimulateError)
-    } else {
-        Ok(())
     }
 }
 
-fn sim_keyboard_event(flags: DWORD, vk: WORD, scan: WORD) -> Result<(), SimulateError> {
+fn keyboard_input(flags: DWORD, vk: WORD, scan: WORD) -> INPUT {
     let mut union: INPUT_u = unsafe { std::mem::zeroed() };
     let inner_union = unsafe { union.ki_mut() };
     *inner_union = KEYBDINPUT {
@@ -59,27 +71,44 @@ fn sim_keyboard_event(flags: DWORD, vk: WORD, scan: WORD) -> Result<(), Simulate
         wScan: scan,
         dwFlags: flags,
         time: 0,
-        dwExtraInfo: 0,
+        dwExtraInfo: extra_info(),
     };
-    let mut input = [INPUT {
+    INPUT {
         type_: INPUT_KEYBOARD,
         u: union,
-    }; 1];
-    let value = unsafe {
+    }
+}
+
+fn send_inputs(mut inputs: Vec<INPUT>) -> Result<(), SimulateError> {
+    let sent = unsafe {
         SendInput(
-            input.len() as UINT,
-            input.as_mut_ptr(),
+            inputs.len() as UINT,
+            inputs.as_mut_ptr(),
             size_of::<INPUT>() as c_int,
         )
     };
-    if value != 1 {
-        Err(SimulateError)
+    let injected = sent as usize;
+    if injected != inputs.len() {
+        Err(SimulateError { injected })
     } else {
         Ok(())
     }
 }
 
-pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
+fn sim_mouse_event(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> Result<(), SimulateError> {
+    send_inputs(vec![mouse_input(flags, data, dx, dy)])
+}
+
+fn sim_keyboard_event(flags: DWORD, vk: WORD, scan: WORD) -> Result<(), SimulateError> {
+    send_inputs(vec![keyboard_input(flags, vk, scan)])
+}
+
+/// Translates a single `EventType` into the zero, one or two `INPUT`s that
+/// `SendInput` needs to inject it, without sending anything. Shared by
+/// `simulate`, which sends each event on its own, and `simulate_batch`, which
+/// collects several events' `INPUT`s into one array so they're injected
+/// atomically.
+fn event_to_inputs(event_type: &EventType) -> Result<Vec<INPUT>, SimulateError> {
     match event_type {
         EventType::KeyPress(key) => {
             let layout = unsafe {
@@ -98,7 +127,11 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
             } else {
                 code
             };
-            sim_keyboard_event(KEYEVENTF_KEYDOWN, code.try_into().unwrap(), 0)
+            Ok(vec![keyboard_input(
+                KEYEVENTF_KEYDOWN,
+                code.try_into().unwrap(),
+                0,
+            )])
         }
         EventType::KeyRelease(key) => {
             let (code, scancode) = get_win_codes(*key);
@@ -115,66 +148,119 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
             } else {
                 code
             };
-            sim_keyboard_event(KEYEVENTF_KEYUP, code.try_into().unwrap(), 0)
+            Ok(vec![keyboard_input(
+                KEYEVENTF_KEYUP,
+                code.try_into().unwrap(),
+                0,
+            )])
         }
-        EventType::ButtonPress(button) => match button {
-            Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
-            Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
-            Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
-            Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XDOWN, 0, 0, (*code).into()),
-        },
-        EventType::ButtonRelease(button) => match button {
-            Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTUP, 0, 0, 0),
-            Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
-            Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
-            Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XUP, 0, 0, (*code).into()),
-        },
+        EventType::ButtonPress(button) => Ok(vec![match button {
+            Button::Left => mouse_input(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
+            Button::Middle => mouse_input(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
+            Button::Right => mouse_input(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
+            Button::Unknown(code) => mouse_input(MOUSEEVENTF_XDOWN, 0, 0, (*code).into()),
+        }]),
+        EventType::ButtonRelease(button) => Ok(vec![match button {
+            Button::Left => mouse_input(MOUSEEVENTF_LEFTUP, 0, 0, 0),
+            Button::Middle => mouse_input(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
+            Button::Right => mouse_input(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
+            Button::Unknown(code) => mouse_input(MOUSEEVENTF_XUP, 0, 0, (*code).into()),
+        }]),
         EventType::Wheel { delta_x, delta_y } => {
+            let mut inputs = Vec::with_capacity(2);
             if *delta_x != 0 {
-                sim_mouse_event(
+                inputs.push(mouse_input(
                     MOUSEEVENTF_HWHEEL,
-                    (c_short::try_from(*delta_x).map_err(|_| SimulateError)? * WHEEL_DELTA) as u32,
+                    (c_short::try_from(*delta_x).map_err(|_| SimulateError { injected: 0 })?
+                        * WHEEL_DELTA) as u32,
                     0,
                     0,
-                )?;
+                ));
             }
 
             if *delta_y != 0 {
-                sim_mouse_event(
+                inputs.push(mouse_input(
                     MOUSEEVENTF_WHEEL,
-                    (c_short::try_from(*delta_y).map_err(|_| SimulateError)? * WHEEL_DELTA) as u32,
+                    (c_short::try_from(*delta_y).map_err(|_| SimulateError { injected: 0 })?
+                        * WHEEL_DELTA) as u32,
                     0,
                     0,
-                )?;
+                ));
             }
-            Ok(())
+            Ok(inputs)
         }
         EventType::MouseMove { x, y } => {
             let width = unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) };
             let height = unsafe { GetSystemMetrics(SM_CYVIRTUALSCREEN) };
             if width == 0 || height == 0 {
-                return Err(SimulateError);
+                return Err(SimulateError { injected: 0 });
             }
 
-            sim_mouse_event(
+            Ok(vec![mouse_input(
                 MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
                 0,
                 (*x as i32 + 1) * 65535 / width,
                 (*y as i32 + 1) * 65535 / height,
-            )
+            )])
         }
     }
 }
 
+pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
+    send_inputs(event_to_inputs(event_type)?)
+}
+
+/// Injects several events as a single `SendInput` call so Windows delivers
+/// them as one atomic batch: no real user input or other process's injected
+/// input can interleave between them, and the whole sequence costs a single
+/// syscall instead of one per event.
+pub fn simulate_batch(events: &[EventType]) -> Result<(), SimulateError> {
+    let mut inputs = Vec::with_capacity(events.len());
+    for event_type in events {
+        inputs.extend(event_to_inputs(event_type)?);
+    }
+    send_inputs(inputs)
+}
+
+/// Builds the `INPUT`s needed to inject `chr`, one `KEYBDINPUT` per UTF-16
+/// code unit so codepoints above U+FFFF (emoji, CJK extension characters,
+/// ...) are sent as a surrogate pair the OS recombines, instead of being
+/// truncated by a `chr as u16` cast.
+fn char_inputs(chr: char, pressed: bool) -> Vec<INPUT> {
+    let mut units = [0u16; 2];
+    let state_flags = if pressed { KEYDOWN } else { KEYUP };
+    chr.encode_utf16(&mut units)
+        .iter()
+        .map(|&unit| keyboard_input((UNICODE | state_flags).into(), 0, unit))
+        .collect()
+}
+
 pub fn simulate_char(chr: char, pressed: bool) -> Result<(), SimulateError> {
-    // send char
+    // `VkKeyScanW` only takes a single UTF-16 code unit, so codepoints above
+    // U+FFFF would have to be truncated to even ask it for a VK - skip the
+    // probe entirely for those and go straight to the Unicode/surrogate path.
+    if chr.len_utf16() > 1 {
+        return send_inputs(char_inputs(chr, pressed));
+    }
+
     let res = unsafe { VkKeyScanW(chr as u16) };
-    let (vk, scan, flags): (i32, u16, u16) = if (res >> 8) & 0xFF == 0 {
-        ((res & 0xFF).into(), 0, 0)
+    if (res >> 8) & 0xFF == 0 {
+        let state_flags = if pressed { KEYDOWN } else { KEYUP };
+        sim_keyboard_event(state_flags.into(), (res & 0xFF) as WORD, 0)
     } else {
-        (0, chr as _, UNICODE)
-    };
+        send_inputs(char_inputs(chr, pressed))
+    }
+}
 
-    let state_flags = if pressed { KEYDOWN } else { KEYUP };
-    sim_keyboard_event((flags | state_flags).into(), vk as _, scan)
+/// Types `text` by simulating Unicode key events character-by-character,
+/// batching every down/up `INPUT` generated along the way into one
+/// `SendInput` call (see `simulate_batch`) so the whole string lands as an
+/// uninterruptible sequence regardless of the active keyboard layout.
+pub fn simulate_string(text: &str) -> Result<(), SimulateError> {
+    let mut inputs = Vec::new();
+    for chr in text.chars() {
+        inputs.extend(char_inputs(chr, true));
+        inputs.extend(char_inputs(chr, false));
+    }
+    send_inputs(inputs)
 }